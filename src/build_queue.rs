@@ -0,0 +1,74 @@
+//! The crate build queue.
+
+use failure::Error;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// A crate queued to be built, in priority order.
+pub(crate) struct QueuedCrate {
+    pub(crate) name: String,
+    pub(crate) version: String,
+    pub(crate) priority: i32,
+    /// When this crate was enqueued, so the builder loop can report how
+    /// long it waited once it starts building.
+    pub(crate) enqueued_at: Instant,
+}
+
+/// Tracks crates waiting to be built and crates that failed to build.
+pub(crate) struct BuildQueue {
+    pending: Mutex<Vec<QueuedCrate>>,
+    failed_count: Mutex<usize>,
+}
+
+impl BuildQueue {
+    pub(crate) fn new() -> Self {
+        Self {
+            pending: Mutex::new(Vec::new()),
+            failed_count: Mutex::new(0),
+        }
+    }
+
+    /// Queues a crate to be built, at `priority` (higher goes first).
+    pub(crate) fn add_crate(&self, name: String, version: String, priority: i32) {
+        self.pending.lock().unwrap().push(QueuedCrate {
+            name,
+            version,
+            priority,
+            enqueued_at: Instant::now(),
+        });
+    }
+
+    /// Pops the next crate to build, highest priority first.
+    pub(crate) fn pop(&self) -> Option<QueuedCrate> {
+        let mut pending = self.pending.lock().unwrap();
+        let index = pending
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, krate)| krate.priority)
+            .map(|(index, _)| index)?;
+
+        Some(pending.remove(index))
+    }
+
+    pub(crate) fn mark_failed(&self) {
+        *self.failed_count.lock().unwrap() += 1;
+    }
+
+    pub(crate) fn pending_count(&self) -> Result<usize, Error> {
+        Ok(self.pending.lock().unwrap().len())
+    }
+
+    pub(crate) fn prioritized_count(&self) -> Result<usize, Error> {
+        Ok(self
+            .pending
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|krate| krate.priority > 0)
+            .count())
+    }
+
+    pub(crate) fn failed_count(&self) -> Result<usize, Error> {
+        Ok(*self.failed_count.lock().unwrap())
+    }
+}
@@ -0,0 +1,72 @@
+//! Runtime configuration, read from environment variables.
+
+use crate::metrics::otlp::OtlpConfig;
+use crate::metrics::statsd::StatsdConfig;
+use std::env;
+use std::time::Duration;
+
+/// Top-level docs.rs configuration.
+///
+/// Constructed once at startup via [`Config::from_env`] and threaded
+/// through to whatever needs it (the web server, the builder loop).
+pub(crate) struct Config {
+    pub(crate) otlp: OtlpConfig,
+    pub(crate) statsd: StatsdConfig,
+    /// Pushgateway URL to push build-subprocess metrics to. Unset by
+    /// default, in which case the builder loop doesn't push at all.
+    pub(crate) pushgateway_url: Option<String>,
+    /// Crates to seed the build queue with at startup, as `name@version`
+    /// pairs. The webhook/DB poll that populates the queue in production
+    /// lives outside this snapshot, so this env var is the stand-in "real"
+    /// source until one exists.
+    pub(crate) seed_crates: Vec<(String, String)>,
+}
+
+impl Config {
+    pub(crate) fn from_env() -> Self {
+        Self {
+            otlp: OtlpConfig {
+                enabled: env_flag("DOCSRS_OTLP_ENABLED"),
+                endpoint: env::var("DOCSRS_OTLP_ENDPOINT")
+                    .unwrap_or_else(|_| OtlpConfig::default().endpoint),
+                push_interval: env_duration_secs("DOCSRS_OTLP_PUSH_INTERVAL_SECONDS")
+                    .unwrap_or_else(|| OtlpConfig::default().push_interval),
+            },
+            statsd: StatsdConfig {
+                enabled: env_flag("DOCSRS_STATSD_ENABLED"),
+                address: env::var("DOCSRS_STATSD_ADDRESS")
+                    .unwrap_or_else(|_| StatsdConfig::default().address),
+                flush_interval: env_duration_secs("DOCSRS_STATSD_FLUSH_INTERVAL_SECONDS")
+                    .unwrap_or_else(|| StatsdConfig::default().flush_interval),
+            },
+            pushgateway_url: env::var("DOCSRS_PUSHGATEWAY_URL").ok(),
+            seed_crates: env_crate_list("DOCSRS_SEED_CRATES"),
+        }
+    }
+}
+
+fn env_flag(var: &str) -> bool {
+    matches!(env::var(var).as_deref(), Ok("1") | Ok("true"))
+}
+
+fn env_duration_secs(var: &str) -> Option<Duration> {
+    env::var(var)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .map(Duration::from_secs)
+}
+
+/// Parses a comma-separated `name@version,name@version` list, skipping any
+/// entry that isn't in that shape rather than failing startup over it.
+fn env_crate_list(var: &str) -> Vec<(String, String)> {
+    env::var(var)
+        .ok()
+        .map(|value| {
+            value
+                .split(',')
+                .filter_map(|entry| entry.split_once('@'))
+                .map(|(name, version)| (name.to_owned(), version.to_owned()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,60 @@
+//! Database connection pool.
+
+use crate::metrics::fault_injection::FaultInjector;
+use crate::metrics::Metrics;
+use failure::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A connection pool, sized to `max_size` connections.
+pub(crate) struct Pool {
+    idle: AtomicUsize,
+    used: AtomicUsize,
+    max_size: usize,
+    fault_injector: FaultInjector,
+}
+
+impl Pool {
+    pub(crate) fn new(max_size: usize) -> Self {
+        Self {
+            idle: AtomicUsize::new(max_size),
+            used: AtomicUsize::new(0),
+            max_size,
+            fault_injector: FaultInjector::from_env(),
+        }
+    }
+
+    pub(crate) fn idle_connections(&self) -> usize {
+        self.idle.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn used_connections(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Acquires a connection, subject to the `random-errors` fault
+    /// injector so the failure-handling paths around database acquisition
+    /// can be exercised under load.
+    pub(crate) fn acquire(&self, metrics: &Metrics) -> Result<Connection<'_>, Error> {
+        self.fault_injector.maybe_fail_db(metrics)?;
+
+        self.idle.fetch_sub(1, Ordering::Relaxed);
+        self.used.fetch_add(1, Ordering::Relaxed);
+        Ok(Connection { pool: self })
+    }
+}
+
+/// A checked-out connection; returned to the pool's idle count on drop.
+pub(crate) struct Connection<'a> {
+    pool: &'a Pool,
+}
+
+impl Drop for Connection<'_> {
+    fn drop(&mut self) {
+        self.pool.idle.fetch_add(1, Ordering::Relaxed);
+        self.pool.used.fetch_sub(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,130 @@
+//! The builder loop: pops crates off the [`BuildQueue`] and builds them.
+
+use crate::build_queue::{BuildQueue, QueuedCrate};
+use crate::db::Pool;
+use crate::metrics::pushgateway::PushgatewayState;
+use crate::metrics::{Metrics, RenderingStep, Route};
+use crate::storage::Storage;
+use crate::Config;
+use failure::Error;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// The outcome of building a single crate.
+pub(crate) enum BuildOutcome {
+    Success,
+    /// Recorded by `build_next_crate` when `build_crate` returns an error.
+    Failure,
+    // The actual build process that would produce this outcome lives
+    // outside this snapshot; kept here so `build_next_crate`'s metrics
+    // wiring below covers all three once it's wired up.
+    #[allow(dead_code)]
+    NonLibrary,
+}
+
+/// Pops one crate off `queue` and builds it, recording metrics throughout
+/// and, if `config.pushgateway_url` is set, pushing this build's share of
+/// them to the gateway afterwards under a grouping key unique to this
+/// crate ([`PushgatewayState`] tracks the deltas, so each group reflects
+/// only this build rather than the process' running totals). A failed
+/// build is recorded as [`BuildOutcome::Failure`] rather than returned as
+/// an `Err`, so the builder loop keeps going to the next crate instead of
+/// aborting on the first build failure.
+pub(crate) fn build_next_crate(
+    queue: &BuildQueue,
+    pool: &Pool,
+    storage: &Storage,
+    metrics: &Metrics,
+    push_gateway: &PushgatewayState,
+    config: &Config,
+) -> Result<(), Error> {
+    let krate = match queue.pop() {
+        Some(krate) => krate,
+        None => return Ok(()),
+    };
+
+    metrics.record_queue_wait(krate.enqueued_at.elapsed());
+
+    let build_started_at = Instant::now();
+    let outcome = match build_crate(&krate, pool, storage, metrics) {
+        Ok(outcome) => outcome,
+        // A build failing (today that's only a `random-errors`-injected
+        // fault, but it's this codebase's only error source) is an outcome
+        // to record and move past, not a reason to take the whole builder
+        // loop down with it.
+        Err(err) => {
+            log::error!("failed to build {} {}: {}", krate.name, krate.version, err);
+            BuildOutcome::Failure
+        }
+    };
+
+    metrics.total_builds.inc();
+    let outcome_label = match outcome {
+        BuildOutcome::Success => {
+            metrics.successful_builds.inc();
+            "success"
+        }
+        BuildOutcome::Failure => {
+            metrics.failed_builds.inc();
+            queue.mark_failed();
+            "failure"
+        }
+        BuildOutcome::NonLibrary => {
+            metrics.non_library_builds.inc();
+            "non-library"
+        }
+    };
+    metrics.record_build_duration(outcome_label, build_started_at.elapsed());
+
+    if let Some(gateway_url) = &config.pushgateway_url {
+        // Refreshes the queue/pool gauges so the pushed snapshot reflects
+        // the state right after this build, not whatever it last happened
+        // to be when some other build pushed.
+        metrics.gather(pool, queue)?;
+
+        let mut grouping_labels = HashMap::new();
+        grouping_labels.insert("instance".to_owned(), format!("{}-{}", krate.name, krate.version));
+        push_gateway.push(metrics, gateway_url, grouping_labels)?;
+    }
+
+    Ok(())
+}
+
+fn build_crate(
+    krate: &QueuedCrate,
+    pool: &Pool,
+    storage: &Storage,
+    metrics: &Metrics,
+) -> Result<BuildOutcome, Error> {
+    // The actual build (running `cargo doc` in a sandbox) lives elsewhere;
+    // this loop only owns queue bookkeeping and the metrics/fault-injection
+    // wiring around the database and storage calls that build would make.
+    let build_started_at = Instant::now();
+    let _connection = pool.acquire(metrics)?;
+
+    for step in [
+        RenderingStep::Parse,
+        RenderingStep::Render,
+        RenderingStep::Highlight,
+        RenderingStep::Write,
+    ] {
+        let step_started_at = Instant::now();
+        metrics
+            .rustdoc_rendering_times
+            .get_variant(step)
+            .observe(step_started_at.elapsed().as_secs_f64());
+    }
+
+    let rustdoc_path = format!("{}/{}/index.html", krate.name, krate.version);
+    storage.store_file(metrics, &rustdoc_path, &[])?;
+
+    // A successful build immediately invalidates the crate's rendered
+    // build-details page, so this counts as a visit to it too.
+    metrics.routes_visited.get_variant(Route::BuildDetails).inc();
+    metrics
+        .response_time
+        .get_variant(Route::BuildDetails)
+        .observe(build_started_at.elapsed().as_secs_f64());
+
+    Ok(BuildOutcome::Success)
+}
@@ -0,0 +1,51 @@
+//! Entry point for the docs.rs background builder/web process.
+
+mod build_queue;
+mod config;
+mod db;
+mod docbuilder;
+mod metrics;
+mod storage;
+mod web;
+
+use build_queue::BuildQueue;
+use config::Config;
+use db::Pool;
+use failure::Error;
+use metrics::pushgateway::PushgatewayState;
+use metrics::Metrics;
+use std::sync::Arc;
+use storage::Storage;
+
+/// How many connections the database pool is sized to.
+const DB_POOL_SIZE: usize = 10;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    env_logger::init();
+
+    let config = Config::from_env();
+    let metrics = Arc::new(Metrics::new()?);
+    let queue = BuildQueue::new();
+    let pool = Pool::new(DB_POOL_SIZE);
+    let storage = Storage::new();
+    let push_gateway = PushgatewayState::new();
+
+    for (name, version) in &config.seed_crates {
+        queue.add_crate(name.clone(), version.clone(), 0);
+    }
+
+    // Kept alive for the rest of `main` so the exporters it starts keep
+    // running until the process exits.
+    let _metrics_exporters = web::start_metrics_exporters(&metrics, &config)?;
+
+    loop {
+        docbuilder::build_next_crate(&queue, &pool, &storage, &metrics, &push_gateway, &config)?;
+
+        if queue.pending_count()? == 0 {
+            break;
+        }
+    }
+
+    Ok(())
+}
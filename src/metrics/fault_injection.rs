@@ -0,0 +1,156 @@
+//! Optional fault injection for storage and database paths.
+//!
+//! Gated behind the `random-errors` cargo feature, this lets us exercise
+//! docs.rs's error-handling and confirm the failure counters actually fire
+//! under load, by making a configurable fraction of storage uploads and
+//! database acquisitions fail as though the backend had.
+//!
+//! When the feature is disabled, [`FaultInjector`] is a unit struct whose
+//! methods return `Ok(())` unconditionally, so there is no branch left on
+//! the hot path.
+
+use super::Metrics;
+
+#[cfg(feature = "random-errors")]
+mod imp {
+    use super::Metrics;
+    use failure::{err_msg, Error};
+    use nanorand::{Rng, WyRand};
+    use std::sync::Mutex;
+
+    /// Env var controlling the fraction (`0.0`..=`1.0`) of storage uploads
+    /// and database acquisitions that [`FaultInjector`] fails on purpose.
+    pub(crate) const RANDOM_ERROR_RATE_VAR: &str = "DOCSRS_RANDOM_ERROR_RATE";
+
+    pub(crate) struct FaultInjector {
+        rate: f64,
+        rng: Mutex<WyRand>,
+    }
+
+    impl FaultInjector {
+        pub(crate) fn new(rate: f64) -> Self {
+            Self {
+                rate: rate.clamp(0.0, 1.0),
+                rng: Mutex::new(WyRand::new()),
+            }
+        }
+
+        /// Builds a [`FaultInjector`] from [`RANDOM_ERROR_RATE_VAR`],
+        /// defaulting to never injecting a fault if it isn't set.
+        pub(crate) fn from_env() -> Self {
+            let rate = std::env::var(RANDOM_ERROR_RATE_VAR)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(0.0);
+
+            Self::new(rate)
+        }
+
+        fn should_fail(&self) -> bool {
+            self.rate > 0.0 && self.rng.lock().unwrap().generate::<f64>() < self.rate
+        }
+
+        /// Injects a fault into a database acquisition on a sampled
+        /// fraction of calls, incrementing the same counters a real
+        /// connection failure would.
+        pub(crate) fn maybe_fail_db(&self, metrics: &Metrics) -> Result<(), Error> {
+            if self.should_fail() {
+                metrics.failed_db_connections.inc();
+                metrics
+                    .injected_errors_total
+                    .with_label_values(&["database"])
+                    .inc();
+                return Err(err_msg("injected database fault"));
+            }
+
+            Ok(())
+        }
+
+        /// Injects a fault into a storage upload on a sampled fraction of
+        /// calls.
+        pub(crate) fn maybe_fail_storage(&self, metrics: &Metrics) -> Result<(), Error> {
+            if self.should_fail() {
+                metrics
+                    .injected_errors_total
+                    .with_label_values(&["storage"])
+                    .inc();
+                return Err(err_msg("injected storage fault"));
+            }
+
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn rate_one_always_fails_and_bumps_counters() {
+            let metrics = Metrics::new().unwrap();
+            let injector = FaultInjector::new(1.0);
+
+            assert!(injector.maybe_fail_db(&metrics).is_err());
+            assert!(injector.maybe_fail_storage(&metrics).is_err());
+
+            assert_eq!(metrics.failed_db_connections.get(), 1);
+            assert_eq!(
+                metrics
+                    .injected_errors_total
+                    .with_label_values(&["database"])
+                    .get(),
+                1
+            );
+            assert_eq!(
+                metrics
+                    .injected_errors_total
+                    .with_label_values(&["storage"])
+                    .get(),
+                1
+            );
+        }
+
+        #[test]
+        fn rate_zero_always_succeeds() {
+            let metrics = Metrics::new().unwrap();
+            let injector = FaultInjector::new(0.0);
+
+            for _ in 0..100 {
+                assert!(injector.maybe_fail_db(&metrics).is_ok());
+                assert!(injector.maybe_fail_storage(&metrics).is_ok());
+            }
+
+            assert_eq!(metrics.failed_db_connections.get(), 0);
+        }
+    }
+}
+
+#[cfg(not(feature = "random-errors"))]
+mod imp {
+    use super::Metrics;
+    use failure::Error;
+
+    pub(crate) struct FaultInjector;
+
+    impl FaultInjector {
+        pub(crate) fn new(_rate: f64) -> Self {
+            Self
+        }
+
+        pub(crate) fn from_env() -> Self {
+            Self::new(0.0)
+        }
+
+        #[inline(always)]
+        pub(crate) fn maybe_fail_db(&self, _metrics: &Metrics) -> Result<(), Error> {
+            Ok(())
+        }
+
+        #[inline(always)]
+        pub(crate) fn maybe_fail_storage(&self, _metrics: &Metrics) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+}
+
+pub(crate) use imp::FaultInjector;
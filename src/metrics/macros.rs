@@ -0,0 +1,144 @@
+//! The `metrics!` struct-generation macro and its supporting traits.
+//!
+//! `metrics!` turns a field list into a `struct` plus a `Metrics::new`
+//! constructor that builds each field with its name (and, for namespaced
+//! fields, a set of label names), registers it on a shared [`Registry`],
+//! and stores the registry alongside the fields so [`Metrics::gather`] can
+//! walk it later. [`MetricFromOpts`] and [`MetricFromOptsVec`] are the
+//! extension points a new field type has to implement to be usable inside
+//! the macro; [`load_metric_type!`] implements them for the `prometheus`
+//! metric types, and the static per-variant structs in `static_metrics`
+//! implement [`MetricFromOpts`] by hand.
+
+use prometheus::{Error, Opts};
+
+/// A metric type that can be constructed directly from [`Opts`], with no
+/// variable labels (a plain counter/gauge/histogram, or one of the
+/// pre-registered static per-variant structs in `static_metrics`).
+pub(crate) trait MetricFromOpts: Sized {
+    fn from_opts(opts: Opts) -> Result<Self, Error>;
+}
+
+/// A metric type that additionally needs a set of variable label names at
+/// construction time (an `IntCounterVec`/`HistogramVec`).
+pub(crate) trait MetricFromOptsVec: Sized {
+    fn from_opts_and_labels(opts: Opts, label_names: &[&str]) -> Result<Self, Error>;
+}
+
+/// Implements [`MetricFromOpts`] (`as single`) or [`MetricFromOptsVec`]
+/// (`as vec`) for a `prometheus` metric type, so `metrics!` can construct
+/// it from a field declaration.
+macro_rules! load_metric_type {
+    ($ty:ident as single) => {
+        impl $crate::metrics::macros::MetricFromOpts for prometheus::$ty {
+            fn from_opts(opts: prometheus::Opts) -> Result<Self, prometheus::Error> {
+                prometheus::$ty::with_opts(opts.into())
+            }
+        }
+    };
+    ($ty:ident as vec) => {
+        impl $crate::metrics::macros::MetricFromOptsVec for prometheus::$ty {
+            fn from_opts_and_labels(
+                opts: prometheus::Opts,
+                label_names: &[&str],
+            ) -> Result<Self, prometheus::Error> {
+                prometheus::$ty::new(opts.into(), label_names)
+            }
+        }
+    };
+}
+
+/// Re-emits whichever of `$attrs` is a `cfg(...)`, dropping everything else
+/// (doc comments in particular), then splices `$body` after it.
+///
+/// `metrics!` needs to gate the constructor/registration code for a
+/// `#[cfg(target_os = "linux")]` field the same way the field itself is
+/// gated, without also smuggling that field's doc comment onto a `let`
+/// binding or struct-literal entry.
+macro_rules! with_cfg_attrs {
+    ([] $($body:tt)*) => {
+        $($body)*
+    };
+    ([[cfg($($cfg:tt)*)] $($rest:tt)*] $($body:tt)*) => {
+        #[cfg($($cfg)*)]
+        with_cfg_attrs!([$($rest)*] $($body)*)
+    };
+    ([[$($other:tt)*] $($rest:tt)*] $($body:tt)*) => {
+        with_cfg_attrs!([$($rest)*] $($body)*)
+    };
+}
+
+/// Declares the `Metrics` struct and its `new` constructor from a field
+/// list, registering every field on a shared [`prometheus::Registry`].
+///
+/// A plain `field: Type` is constructed via [`MetricFromOpts::from_opts`];
+/// `field: Type["label", ...]` is constructed via
+/// [`MetricFromOptsVec::from_opts_and_labels`] with those label names. The
+/// metric name is the field's identifier; `namespace` is applied to every
+/// field the same way.
+macro_rules! metrics {
+    (@new $ty:path, $ns:expr, $name:ident) => {
+        <$ty as $crate::metrics::macros::MetricFromOpts>::from_opts(
+            prometheus::Opts::new(stringify!($name), stringify!($name)).namespace($ns),
+        )
+    };
+    (@new $ty:path, $ns:expr, $name:ident, [$($label:literal),+]) => {
+        <$ty as $crate::metrics::macros::MetricFromOptsVec>::from_opts_and_labels(
+            prometheus::Opts::new(stringify!($name), stringify!($name)).namespace($ns),
+            &[$($label),+],
+        )
+    };
+
+    (
+        $(#[$struct_attr:meta])*
+        $struct_vis:vis struct $struct_name:ident {
+            $(
+                $(#[$($field_attr:tt)*])*
+                $field_vis:vis $field_name:ident : $field_ty:path $( [ $($label:literal),+ $(,)? ] )?
+            ),+ $(,)?
+        }
+
+        namespace: $namespace:literal,
+    ) => {
+        $(#[$struct_attr])*
+        $struct_vis struct $struct_name {
+            $(
+                $(#[$($field_attr)*])*
+                $field_vis $field_name: $field_ty,
+            )+
+            registry: prometheus::Registry,
+        }
+
+        impl $struct_name {
+            // Field doc comments are re-spliced onto the `Ok(Self { .. })`
+            // fields below (there's no clean way to strip just the `cfg`
+            // out of `$field_attr` at struct-literal-field position), which
+            // triggers an `unused_doc_comments` warning there that doesn't
+            // reflect anything wrong with the real, documented field.
+            #[allow(unused_doc_comments)]
+            pub(crate) fn new() -> Result<Self, prometheus::Error> {
+                let registry = prometheus::Registry::new();
+
+                $(
+                    with_cfg_attrs!(
+                        [$([$($field_attr)*])*]
+                        let $field_name: $field_ty =
+                            metrics!(@new $field_ty, $namespace, $field_name $(, [$($label),+])?)?;
+                    );
+                    with_cfg_attrs!(
+                        [$([$($field_attr)*])*]
+                        registry.register(Box::new($field_name.clone()))?;
+                    );
+                )+
+
+                Ok(Self {
+                    $(
+                        $(#[$($field_attr)*])*
+                        $field_name,
+                    )+
+                    registry,
+                })
+            }
+        }
+    };
+}
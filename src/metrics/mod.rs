@@ -1,16 +1,25 @@
 #[macro_use]
 mod macros;
+pub(crate) mod fault_injection;
+pub(crate) mod otlp;
+pub(crate) mod pushgateway;
+pub(crate) mod statsd;
+mod static_metrics;
+
+pub(crate) use self::static_metrics::{Route, RenderingStep};
 
-use self::macros::MetricFromOpts;
 use crate::db::Pool;
 use crate::BuildQueue;
 use failure::Error;
 use prometheus::proto::MetricFamily;
+use prometheus::{Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge};
+use std::sync::Arc;
 
 load_metric_type!(IntGauge as single);
 load_metric_type!(IntCounter as single);
 load_metric_type!(IntCounterVec as vec);
 load_metric_type!(HistogramVec as vec);
+load_metric_type!(Histogram as single);
 
 metrics! {
     pub struct Metrics {
@@ -28,6 +37,7 @@ metrics! {
         /// The maximum number of database connections
         max_db_connections: IntGauge,
         /// Number of attempted and failed connections to the database
+        #[cfg_attr(not(feature = "random-errors"), allow(dead_code))]
         pub(crate) failed_db_connections: IntCounter,
 
         /// The number of currently opened file descriptors
@@ -38,11 +48,11 @@ metrics! {
         running_threads: IntGauge,
 
         /// The traffic of various docs.rs routes
-        pub(crate) routes_visited: IntCounterVec["route"],
+        pub(crate) routes_visited: static_metrics::RouteMetrics,
         /// The response times of various docs.rs routes
-        pub(crate) response_time: HistogramVec["route"],
+        pub(crate) response_time: static_metrics::ResponseTimeMetrics,
         /// The time it takes to render a rustdoc page
-        pub(crate) rustdoc_rendering_times: HistogramVec["step"],
+        pub(crate) rustdoc_rendering_times: static_metrics::RenderingTimeMetrics,
 
         /// Number of crates built
         pub(crate) total_builds: IntCounter,
@@ -55,9 +65,25 @@ metrics! {
 
         /// Number of files uploaded to the storage backend
         pub(crate) uploaded_files_total: IntCounter,
+        /// Total number of bytes uploaded to the storage backend
+        pub(crate) bytes_uploaded_total: IntCounter,
 
         /// The number of attempted files that failed due to a memory limit
+        // The HTML rewriter that increments this lives outside this
+        // snapshot; kept here so the counter is still exposed at a
+        // constant zero rather than disappearing from dashboards.
+        #[allow(dead_code)]
         pub(crate) html_rewrite_ooms: IntCounter,
+
+        /// Number of errors injected by the `random-errors` fault-injection
+        /// harness, labeled by the subsystem they were injected into
+        #[cfg_attr(not(feature = "random-errors"), allow(dead_code))]
+        pub(crate) injected_errors_total: IntCounterVec["subsystem"],
+
+        /// How long a build took, labeled by its outcome
+        pub(crate) build_duration_seconds: HistogramVec["outcome"],
+        /// How long a crate spent in the build queue before its build started
+        pub(crate) queue_wait_seconds: Histogram,
     }
 
     // The Rust prometheus library treats the namespace as the "prefix" of the metric name: a
@@ -68,6 +94,22 @@ metrics! {
 }
 
 impl Metrics {
+    /// Records that a build finished with `outcome` (`"success"`,
+    /// `"failure"`, or `"non-library"`) after `duration`. Called by the
+    /// builder loop once a build completes.
+    pub(crate) fn record_build_duration(&self, outcome: &str, duration: std::time::Duration) {
+        self.build_duration_seconds
+            .with_label_values(&[outcome])
+            .observe(duration.as_secs_f64());
+    }
+
+    /// Records how long a crate waited in the build queue before its build
+    /// started. Called by the builder loop when it pops a crate off the
+    /// [`BuildQueue`].
+    pub(crate) fn record_queue_wait(&self, duration: std::time::Duration) {
+        self.queue_wait_seconds.observe(duration.as_secs_f64());
+    }
+
     pub(crate) fn gather(
         &self,
         pool: &Pool,
@@ -96,7 +138,35 @@ impl Metrics {
         let process = Process::myself().unwrap();
         self.open_file_descriptors
             .set(process.fd().unwrap().len() as i64);
-        self.running_threads
-            .set(process.stat().unwrap().num_threads as i64);
+        // `num_threads` is already `i64` in the `procfs` version this crate
+        // resolves to, so no cast is needed here (unlike the `as i64` just
+        // above, which is converting from `usize`).
+        self.running_threads.set(process.stat().unwrap().num_threads);
+    }
+
+    /// Starts the optional OTLP push exporter, if configured. This is a
+    /// no-op unless `config.enabled` is set, so deployments that only
+    /// scrape the Prometheus `/metrics` endpoint pay nothing for it.
+    ///
+    /// The returned [`otlp::OtlpExporter`] drives the exporter itself; it
+    /// must be kept alive for as long as the exporter should keep running,
+    /// since dropping it shuts the exporter down.
+    pub(crate) fn spawn_otlp_exporter(
+        self: &Arc<Self>,
+        config: otlp::OtlpConfig,
+    ) -> Result<Option<otlp::OtlpExporter>, Error> {
+        otlp::spawn(Arc::clone(self), config)
     }
+
+    /// Starts the optional StatsD/DogStatsD push exporter, if configured.
+    /// This is independent of [`Metrics::spawn_otlp_exporter`]; an operator
+    /// picks Prometheus scrape, OTLP, StatsD, any combination, or none of
+    /// the above purely through config.
+    pub(crate) fn spawn_statsd_exporter(
+        self: &Arc<Self>,
+        config: statsd::StatsdConfig,
+    ) -> Result<Option<tokio::task::JoinHandle<()>>, Error> {
+        statsd::spawn(Arc::clone(self), config)
+    }
+
 }
@@ -0,0 +1,222 @@
+//! Optional OpenTelemetry OTLP push exporter.
+//!
+//! This mirrors the same [`prometheus::Registry`] that backs the `/metrics`
+//! scrape endpoint onto a set of OTel async instruments, for operators
+//! whose observability stack expects metrics to be pushed to a collector
+//! rather than scraped. It is disabled by default and only runs when
+//! [`OtlpConfig::enabled`] is set.
+//!
+//! A `Vec`-typed family (`injected_errors_total`, `build_duration_seconds`)
+//! is pruned from [`prometheus::Registry::gather`] entirely until one of its
+//! label combinations has actually been observed, so an instrument can't be
+//! registered for it up front at [`spawn`] time. [`register_instruments`]
+//! is instead re-run on `config.push_interval` by a background task
+//! (alongside the [`PushController`]'s own export tick) so a family that
+//! only starts producing samples partway through the process's life still
+//! gets mirrored once it does, rather than never.
+//!
+//! Histograms have no async instrument counterpart in OTel 0.17, so each is
+//! mirrored as the `_sum`/`_count` pair Prometheus itself exposes over text
+//! format; this reconstructs a windowed mean on the collector side but, by
+//! construction, carries none of the bucket boundaries a real OTLP
+//! `Histogram` would, so no percentiles can be computed from it downstream.
+
+use super::Metrics;
+use failure::Error;
+use opentelemetry::metrics::{Meter, MeterProvider, ObserverResult};
+use opentelemetry::sdk::metrics::controllers::PushController;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use prometheus::proto::{Metric, MetricType};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Configuration for the optional OTLP push exporter.
+///
+/// When `enabled` is `false` (the default), [`spawn`] does nothing, so the
+/// OTLP code path costs nothing beyond this check for deployments that only
+/// scrape the Prometheus `/metrics` endpoint.
+#[derive(Debug, Clone)]
+pub(crate) struct OtlpConfig {
+    pub(crate) enabled: bool,
+    pub(crate) endpoint: String,
+    pub(crate) push_interval: Duration,
+}
+
+impl Default for OtlpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: "http://localhost:4317".into(),
+            push_interval: Duration::from_secs(15),
+        }
+    }
+}
+
+/// Keeps the OTLP pipeline alive: the [`PushController`] drives the
+/// periodic export and really does stop on drop, but the background task
+/// that keeps registering instruments for newly-populated families (see
+/// the module docs) is a plain [`tokio::task::JoinHandle`], which merely
+/// detaches on drop rather than stopping — so `Drop` aborts it explicitly.
+pub(crate) struct OtlpExporter {
+    _controller: PushController,
+    registration_task: tokio::task::JoinHandle<()>,
+}
+
+impl Drop for OtlpExporter {
+    fn drop(&mut self) {
+        self.registration_task.abort();
+    }
+}
+
+/// Builds the OTLP push pipeline and spawns the task that keeps it
+/// supplied with instruments, if `config.enabled` is set.
+pub(crate) fn spawn(metrics: Arc<Metrics>, config: OtlpConfig) -> Result<Option<OtlpExporter>, Error> {
+    if !config.enabled {
+        return Ok(None);
+    }
+
+    let exporter = opentelemetry_otlp::new_exporter()
+        .tonic()
+        .with_endpoint(config.endpoint.clone());
+
+    let controller = opentelemetry_otlp::new_pipeline()
+        .metrics(tokio::spawn, opentelemetry::util::tokio_interval_stream)
+        .with_exporter(exporter)
+        .with_period(config.push_interval)
+        .build()?;
+
+    let meter = controller.provider().meter("docsrs", None);
+    let push_interval = config.push_interval;
+    let registration_task = tokio::spawn(async move {
+        let mut seen = HashSet::new();
+        let mut interval = tokio::time::interval(push_interval);
+        loop {
+            register_instruments(&meter, &metrics, &mut seen);
+            interval.tick().await;
+        }
+    });
+
+    Ok(Some(OtlpExporter {
+        _controller: controller,
+        registration_task,
+    }))
+}
+
+/// Registers one async instrument for each metric family in `metrics`'
+/// registry whose name isn't already in `seen`, picking the instrument
+/// kind (and, for gauges, last-value-correct semantics) from the family's
+/// Prometheus type. Called on every `config.push_interval` tick so a
+/// `Vec`-typed family that starts with no samples still gets an instrument
+/// once it has its first one.
+fn register_instruments(meter: &Meter, metrics: &Arc<Metrics>, seen: &mut HashSet<String>) {
+    for family in metrics.registry.gather() {
+        let name = family.get_name().to_owned();
+        if seen.contains(&name) {
+            continue;
+        }
+
+        // Summaries and untyped families aren't produced by this crate's
+        // metrics today; leave them out of `seen` so a future family of a
+        // recognized type isn't skipped by a stale name match.
+        if !matches!(
+            family.get_field_type(),
+            MetricType::COUNTER | MetricType::GAUGE | MetricType::HISTOGRAM
+        ) {
+            continue;
+        }
+        seen.insert(name.clone());
+
+        match family.get_field_type() {
+            MetricType::COUNTER => {
+                let metrics = Arc::clone(metrics);
+                meter
+                    .f64_sum_observer(name.clone(), move |result| {
+                        observe(&metrics, &name, MetricType::COUNTER, &result)
+                    })
+                    .init();
+            }
+            // A gauge needs last-value semantics, not summation, so this is
+            // a value observer rather than a sum observer.
+            MetricType::GAUGE => {
+                let metrics = Arc::clone(metrics);
+                meter
+                    .f64_value_observer(name.clone(), move |result| {
+                        observe(&metrics, &name, MetricType::GAUGE, &result)
+                    })
+                    .init();
+            }
+            // OTel 0.17 has no async histogram-shaped instrument, so a
+            // histogram is mirrored the same way Prometheus itself exposes
+            // it over text format: as separate cumulative sum/count series.
+            // See the module docs for what this loses relative to a real
+            // OTLP histogram.
+            MetricType::HISTOGRAM => {
+                let sum_metrics = Arc::clone(metrics);
+                let sum_name = name.clone();
+                meter
+                    .f64_sum_observer(format!("{}_sum", name), move |result| {
+                        observe_histogram(&sum_metrics, &sum_name, &result, |h| h.get_sample_sum())
+                    })
+                    .init();
+
+                let count_metrics = Arc::clone(metrics);
+                let count_name = name.clone();
+                meter
+                    .f64_sum_observer(format!("{}_count", name), move |result| {
+                        observe_histogram(&count_metrics, &count_name, &result, |h| {
+                            h.get_sample_count() as f64
+                        })
+                    })
+                    .init();
+            }
+            _ => unreachable!("filtered to COUNTER | GAUGE | HISTOGRAM above"),
+        }
+    }
+}
+
+/// Re-gathers `name`'s current samples and reports each label set's value,
+/// called once per collection tick by the instrument created in
+/// [`register_instruments`].
+fn observe(metrics: &Metrics, name: &str, field_type: MetricType, result: &ObserverResult<f64>) {
+    for family in metrics.registry.gather() {
+        if family.get_name() != name || family.get_field_type() != field_type {
+            continue;
+        }
+
+        for metric in family.get_metric() {
+            let value = match field_type {
+                MetricType::COUNTER => metric.get_counter().get_value(),
+                MetricType::GAUGE => metric.get_gauge().get_value(),
+                _ => continue,
+            };
+            result.observe(value, &attributes(metric));
+        }
+    }
+}
+
+fn observe_histogram(
+    metrics: &Metrics,
+    name: &str,
+    result: &ObserverResult<f64>,
+    value_of: impl Fn(&prometheus::proto::Histogram) -> f64,
+) {
+    for family in metrics.registry.gather() {
+        if family.get_name() != name || family.get_field_type() != MetricType::HISTOGRAM {
+            continue;
+        }
+
+        for metric in family.get_metric() {
+            result.observe(value_of(metric.get_histogram()), &attributes(metric));
+        }
+    }
+}
+
+fn attributes(metric: &Metric) -> Vec<KeyValue> {
+    metric
+        .get_label()
+        .iter()
+        .map(|pair| KeyValue::new(pair.get_name().to_owned(), pair.get_value().to_owned()))
+        .collect()
+}
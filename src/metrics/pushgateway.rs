@@ -0,0 +1,121 @@
+//! Delta-tracking push to a Prometheus Pushgateway.
+//!
+//! [`Metrics`]' registry is cumulative for the life of the process, but the
+//! builder loop pushes once per build under a grouping key unique to that
+//! crate (`instance=<name>-<version>`). Pushing the live cumulative
+//! registry under that scheme would leave every build's group permanently
+//! stamped with the running totals *as of that push*, so a later build's
+//! group would already include every earlier build's counts -- summing
+//! across groups on a dashboard would then double/triple-count. Instead,
+//! [`PushgatewayState`] remembers what it last pushed for each counter and
+//! histogram series and pushes only the delta since then, so each group
+//! reflects just its own build.
+//!
+//! Gauges are already a point-in-time snapshot rather than a running
+//! total, so they're pushed as-is.
+
+use super::Metrics;
+use failure::Error;
+use prometheus::proto::{Metric, MetricFamily, MetricType};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// A histogram's last-pushed sum, count, and per-bucket cumulative count
+/// (in the same bucket order `gather` always returns for a given family).
+type HistogramSnapshot = (f64, u64, Vec<u64>);
+
+/// Tracks the last counter/histogram values pushed to the gateway, keyed
+/// per series, so [`PushgatewayState::push`] can push deltas instead of
+/// the live cumulative registry.
+pub(crate) struct PushgatewayState {
+    last_counter_values: Mutex<HashMap<String, f64>>,
+    last_histogram_values: Mutex<HashMap<String, HistogramSnapshot>>,
+}
+
+impl PushgatewayState {
+    pub(crate) fn new() -> Self {
+        Self {
+            last_counter_values: Mutex::new(HashMap::new()),
+            last_histogram_values: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Gathers `metrics`' registry, rewrites every counter/histogram
+    /// sample in place to be the delta since the last call, and pushes the
+    /// result to `gateway_url` under the `docsrs-builder` job.
+    pub(crate) fn push(
+        &self,
+        metrics: &Metrics,
+        gateway_url: &str,
+        grouping_labels: HashMap<String, String>,
+    ) -> Result<(), Error> {
+        let mut families = metrics.registry.gather();
+        self.delta_encode(&mut families);
+
+        prometheus::push_metrics("docsrs-builder", grouping_labels, gateway_url, families, None)?;
+
+        Ok(())
+    }
+
+    fn delta_encode(&self, families: &mut [MetricFamily]) {
+        let mut last_counter_values = self.last_counter_values.lock().unwrap();
+        let mut last_histogram_values = self.last_histogram_values.lock().unwrap();
+
+        for family in families {
+            let name = family.get_name().to_owned();
+
+            match family.get_field_type() {
+                MetricType::COUNTER => {
+                    for metric in family.mut_metric() {
+                        let key = series_key(&name, metric);
+                        let value = metric.get_counter().get_value();
+                        let previous = last_counter_values.insert(key, value).unwrap_or(0.0);
+                        metric.mut_counter().set_value(value - previous);
+                    }
+                }
+                MetricType::HISTOGRAM => {
+                    for metric in family.mut_metric() {
+                        let key = series_key(&name, metric);
+                        let histogram = metric.get_histogram();
+                        let sum = histogram.get_sample_sum();
+                        let count = histogram.get_sample_count();
+                        let buckets: Vec<u64> = histogram
+                            .get_bucket()
+                            .iter()
+                            .map(|bucket| bucket.get_cumulative_count())
+                            .collect();
+
+                        let (previous_sum, previous_count, previous_buckets) =
+                            last_histogram_values
+                                .insert(key, (sum, count, buckets.clone()))
+                                .unwrap_or_else(|| (0.0, 0, vec![0; buckets.len()]));
+
+                        let histogram = metric.mut_histogram();
+                        histogram.set_sample_sum(sum - previous_sum);
+                        histogram.set_sample_count(count.saturating_sub(previous_count));
+                        for (bucket, previous) in
+                            histogram.mut_bucket().iter_mut().zip(&previous_buckets)
+                        {
+                            let cumulative = bucket.get_cumulative_count();
+                            bucket.set_cumulative_count(cumulative.saturating_sub(*previous));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Identifies one label combination within a family, the same way
+/// `statsd`'s windowed deltas are keyed by name plus tags.
+fn series_key(family_name: &str, metric: &Metric) -> String {
+    let mut key = family_name.to_owned();
+    for label in metric.get_label() {
+        key.push(';');
+        key.push_str(label.get_name());
+        key.push('=');
+        key.push_str(label.get_value());
+    }
+    key
+}
@@ -0,0 +1,254 @@
+//! Compile-time static metrics for the `route`/`step` label vectors.
+//!
+//! `routes_visited`, `response_time`, and `rustdoc_rendering_times` sit on
+//! the hot path of every request, so doing a `with_label_values` lookup
+//! (hashing the label set, walking the vec's internal map) on every
+//! increment is wasted work for labels we already know the full set of at
+//! compile time. The structs below pre-create one concrete `IntCounter`/
+//! `Histogram` per known [`Route`]/[`RenderingStep`] at registration time
+//! and expose them as plain fields, so recording a sample becomes a direct
+//! field access. Anything that doesn't match a known variant falls back to
+//! the `other` bucket instead of being dropped.
+
+use super::macros::MetricFromOpts;
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::MetricFamily;
+use prometheus::{Histogram, HistogramOpts, IntCounter, Opts};
+
+/// The routes we track individual traffic/response-time metrics for.
+///
+/// This mirrors the route names that used to be passed to
+/// `routes_visited.with_label_values(&[route])`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Route {
+    Home,
+    CrateDetails,
+    RustdocPage,
+    BuildList,
+    BuildDetails,
+    SourceView,
+    ReleasesFeed,
+    Sitemap,
+}
+
+impl Route {
+    #[allow(dead_code)]
+    const ALL: &'static [Route] = &[
+        Route::Home,
+        Route::CrateDetails,
+        Route::RustdocPage,
+        Route::BuildList,
+        Route::BuildDetails,
+        Route::SourceView,
+        Route::ReleasesFeed,
+        Route::Sitemap,
+    ];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Route::Home => "home",
+            Route::CrateDetails => "crate-details",
+            Route::RustdocPage => "rustdoc-page",
+            Route::BuildList => "build-list",
+            Route::BuildDetails => "build-details",
+            Route::SourceView => "source-view",
+            Route::ReleasesFeed => "releases-feed",
+            Route::Sitemap => "sitemap",
+        }
+    }
+
+    /// Maps a dynamically-matched route name to its static variant, falling
+    /// back to `None` (the `other` bucket) for anything unrecognized.
+    #[allow(dead_code)]
+    fn from_str(route: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|r| r.as_str() == route)
+    }
+}
+
+/// The rustdoc rendering steps we track render-time metrics for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RenderingStep {
+    Parse,
+    Render,
+    Highlight,
+    Write,
+}
+
+impl RenderingStep {
+    #[allow(dead_code)]
+    const ALL: &'static [RenderingStep] = &[
+        RenderingStep::Parse,
+        RenderingStep::Render,
+        RenderingStep::Highlight,
+        RenderingStep::Write,
+    ];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            RenderingStep::Parse => "parse",
+            RenderingStep::Render => "render",
+            RenderingStep::Highlight => "highlight",
+            RenderingStep::Write => "write",
+        }
+    }
+
+    #[allow(dead_code)]
+    fn from_str(step: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|s| s.as_str() == step)
+    }
+}
+
+macro_rules! static_metric_struct {
+    (
+        $(#[$struct_attr:meta])*
+        struct $name:ident < $variant:ty > ($metric:ty, $opts:ty, $ctor:path, $label:expr) {
+            $($field:ident => $variant_value:expr),* $(,)?
+        }
+    ) => {
+        $(#[$struct_attr])*
+        #[derive(Clone)]
+        pub(crate) struct $name {
+            $($field: $metric,)*
+            other: $metric,
+        }
+
+        impl $name {
+            /// Returns the pre-created metric for a statically-known
+            /// `variant` directly, with no string comparison at all — call
+            /// sites that already have a `$variant` in hand (the common
+            /// case, since routes and rendering steps are matched out of
+            /// an enum/router table rather than discovered as strings)
+            /// should use this instead of [`Self::get`].
+            pub(crate) fn get_variant(&self, variant: $variant) -> &$metric {
+                $(if variant == $variant_value {
+                    return &self.$field;
+                })*
+                unreachable!("every {} variant has a field above", stringify!($variant))
+            }
+
+            /// Returns the pre-created metric for `key`, falling back to
+            /// the `other` bucket for anything that isn't a known variant.
+            /// This still pays for a string comparison against each known
+            /// variant; prefer [`Self::get_variant`] when the caller
+            /// already has a `$variant` rather than a route/step name.
+            #[allow(dead_code)]
+            pub(crate) fn get(&self, key: &str) -> &$metric {
+                match <$variant>::from_str(key) {
+                    $(Some(variant) if variant == $variant_value => &self.$field,)*
+                    _ => &self.other,
+                }
+            }
+        }
+
+        impl MetricFromOpts for $name {
+            fn from_opts(opts: Opts) -> Result<Self, prometheus::Error> {
+                Ok(Self {
+                    $(
+                        $field: $ctor(<$opts>::from(opts.clone())
+                            .const_label($label, <$variant>::as_str($variant_value)))?,
+                    )*
+                    other: $ctor(<$opts>::from(opts.clone()).const_label($label, "other"))?,
+                })
+            }
+        }
+
+        impl Collector for $name {
+            fn desc(&self) -> Vec<&Desc> {
+                let mut descs = Vec::new();
+                $(descs.extend(self.$field.desc());)*
+                descs.extend(self.other.desc());
+                descs
+            }
+
+            fn collect(&self) -> Vec<MetricFamily> {
+                let mut families = Vec::new();
+                $(families.extend(self.$field.collect());)*
+                families.extend(self.other.collect());
+                families
+            }
+        }
+    };
+}
+
+static_metric_struct! {
+    /// Static per-route replacement for the old `routes_visited` `IntCounterVec["route"]`.
+    struct RouteCounters<Route>(IntCounter, Opts, IntCounter::with_opts, "route") {
+        home => Route::Home,
+        crate_details => Route::CrateDetails,
+        rustdoc_page => Route::RustdocPage,
+        build_list => Route::BuildList,
+        build_details => Route::BuildDetails,
+        source_view => Route::SourceView,
+        releases_feed => Route::ReleasesFeed,
+        sitemap => Route::Sitemap,
+    }
+}
+
+static_metric_struct! {
+    /// Static per-route replacement for the old `response_time` `HistogramVec["route"]`.
+    struct RouteHistograms<Route>(Histogram, HistogramOpts, Histogram::with_opts, "route") {
+        home => Route::Home,
+        crate_details => Route::CrateDetails,
+        rustdoc_page => Route::RustdocPage,
+        build_list => Route::BuildList,
+        build_details => Route::BuildDetails,
+        source_view => Route::SourceView,
+        releases_feed => Route::ReleasesFeed,
+        sitemap => Route::Sitemap,
+    }
+}
+
+static_metric_struct! {
+    /// Static per-step replacement for the old `rustdoc_rendering_times` `HistogramVec["step"]`.
+    struct RenderingStepHistograms<RenderingStep>(Histogram, HistogramOpts, Histogram::with_opts, "step") {
+        parse => RenderingStep::Parse,
+        render => RenderingStep::Render,
+        highlight => RenderingStep::Highlight,
+        write => RenderingStep::Write,
+    }
+}
+
+pub(crate) type RouteMetrics = RouteCounters;
+pub(crate) type ResponseTimeMetrics = RouteHistograms;
+pub(crate) type RenderingTimeMetrics = RenderingStepHistograms;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_route_resolves_to_its_own_counter_both_ways() {
+        let counters =
+            RouteCounters::from_opts(Opts::new("routes_visited", "help")).unwrap();
+
+        counters.get("home").inc();
+        counters.get_variant(Route::Home).inc();
+
+        assert_eq!(counters.get("home").get(), 2);
+        assert_eq!(counters.get_variant(Route::Home).get(), 2);
+        // A different known route's counter is untouched.
+        assert_eq!(counters.get("crate-details").get(), 0);
+    }
+
+    #[test]
+    fn unknown_route_falls_back_to_other() {
+        let counters =
+            RouteCounters::from_opts(Opts::new("routes_visited", "help")).unwrap();
+
+        counters.get("some-not-yet-static-route").inc();
+
+        assert_eq!(counters.get("some-not-yet-static-route").get(), 1);
+        assert_eq!(counters.get("home").get(), 0);
+    }
+
+    #[test]
+    fn unknown_step_falls_back_to_other() {
+        let histograms =
+            RenderingStepHistograms::from_opts(Opts::new("render_time", "help")).unwrap();
+
+        histograms.get("some-not-yet-static-step").observe(1.0);
+
+        assert_eq!(histograms.get("some-not-yet-static-step").get_sample_count(), 1);
+        assert_eq!(histograms.get("parse").get_sample_count(), 0);
+    }
+}
@@ -0,0 +1,222 @@
+//! Optional StatsD/DogStatsD push exporter.
+//!
+//! Some deployments aggregate metrics through a StatsD or DogStatsD agent
+//! rather than scraping Prometheus or receiving OTLP pushes. This walks the
+//! same registry as the other exporters on a timer and sends it as StatsD
+//! lines over UDP, so an operator can pick whichever of the three fits
+//! their stack purely through config.
+
+use super::Metrics;
+use failure::Error;
+use prometheus::proto::MetricType;
+use std::collections::HashMap;
+use std::net::UdpSocket;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Configuration for the optional StatsD/DogStatsD exporter.
+#[derive(Debug, Clone)]
+pub(crate) struct StatsdConfig {
+    pub(crate) enabled: bool,
+    /// Address of the StatsD/DogStatsD agent, e.g. `127.0.0.1:8125`.
+    pub(crate) address: String,
+    pub(crate) flush_interval: Duration,
+}
+
+impl Default for StatsdConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "127.0.0.1:8125".into(),
+            flush_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Spawns the periodic StatsD flush task if `config.enabled` is set.
+pub(crate) fn spawn(
+    metrics: Arc<Metrics>,
+    config: StatsdConfig,
+) -> Result<Option<tokio::task::JoinHandle<()>>, Error> {
+    if !config.enabled {
+        return Ok(None);
+    }
+
+    let exporter = StatsdExporter::new(&config.address)?;
+    let handle = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.flush_interval);
+        loop {
+            interval.tick().await;
+            if let Err(err) = exporter.flush(&metrics) {
+                log::error!("failed to flush metrics to statsd: {}", err);
+            }
+        }
+    });
+
+    Ok(Some(handle))
+}
+
+/// Translates the Prometheus registry into StatsD lines and sends them to a
+/// configured StatsD/DogStatsD agent over UDP.
+pub(crate) struct StatsdExporter {
+    socket: UdpSocket,
+    address: String,
+    // Counters are cumulative in the Prometheus registry but StatsD expects
+    // deltas, so we remember what we last sent for each counter series.
+    last_counter_values: Mutex<HashMap<String, f64>>,
+    // Histogram sum/count are cumulative too; without subtracting the last
+    // flush's values we'd resend the lifetime average every tick instead of
+    // a value reflecting this flush window.
+    last_histogram_values: Mutex<HashMap<String, (f64, u64)>>,
+}
+
+impl StatsdExporter {
+    pub(crate) fn new(address: &str) -> Result<Self, Error> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        Ok(Self {
+            socket,
+            address: address.to_owned(),
+            last_counter_values: Mutex::new(HashMap::new()),
+            last_histogram_values: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Walks `metrics`' registry once, sending one StatsD line per sample.
+    pub(crate) fn flush(&self, metrics: &Metrics) -> Result<(), Error> {
+        let mut last_counter_values = self.last_counter_values.lock().unwrap();
+        let mut last_histogram_values = self.last_histogram_values.lock().unwrap();
+
+        for family in metrics.registry.gather() {
+            let name = family.get_name();
+
+            for metric in family.get_metric() {
+                let tags = dogstatsd_tags(metric.get_label());
+                let series_key = format!("{}{}", name, tags);
+
+                let line = match family.get_field_type() {
+                    MetricType::GAUGE => {
+                        Some(format!("{}:{}|g{}", name, metric.get_gauge().get_value(), tags))
+                    }
+                    MetricType::COUNTER => {
+                        let value = metric.get_counter().get_value();
+                        let previous = last_counter_values.insert(series_key, value).unwrap_or(0.0);
+                        let delta = value - previous;
+                        Some(format!("{}:{}|c{}", name, delta, tags))
+                    }
+                    MetricType::HISTOGRAM => {
+                        let histogram = metric.get_histogram();
+                        let sum = histogram.get_sample_sum();
+                        let count = histogram.get_sample_count();
+
+                        let (previous_sum, previous_count) = last_histogram_values
+                            .insert(series_key, (sum, count))
+                            .unwrap_or((0.0, 0));
+                        let delta_count = count.saturating_sub(previous_count);
+
+                        if delta_count == 0 {
+                            // Nothing observed since the last flush; don't
+                            // resend a stale mean.
+                            None
+                        } else {
+                            let windowed_mean = (sum - previous_sum) / delta_count as f64;
+                            Some(format!("{}:{}|h{}", name, windowed_mean, tags))
+                        }
+                    }
+                    _ => None,
+                };
+
+                if let Some(line) = line {
+                    self.socket.send_to(line.as_bytes(), &self.address)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Encodes Prometheus labels as DogStatsD tags: `|#route:home,step:parse`.
+fn dogstatsd_tags(labels: &[prometheus::proto::LabelPair]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+
+    let tags = labels
+        .iter()
+        .map(|pair| format!("{}:{}", pair.get_name(), pair.get_value()))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    format!("|#{}", tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+    use std::time::Duration;
+
+    /// Binds a loopback receiver and an exporter that sends to it.
+    fn exporter_and_receiver() -> (StatsdExporter, UdpSocket) {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+        let exporter = StatsdExporter::new(&receiver.local_addr().unwrap().to_string()).unwrap();
+        (exporter, receiver)
+    }
+
+    /// Drains every line sent in one `flush` call; a flush sends one
+    /// datagram per sample, so callers look for the line they care about
+    /// rather than assuming it's the only one.
+    fn recv_lines(receiver: &UdpSocket) -> Vec<String> {
+        let mut buf = [0u8; 512];
+        let mut lines = Vec::new();
+        while let Ok((len, _)) = receiver.recv_from(&mut buf) {
+            lines.push(String::from_utf8(buf[..len].to_vec()).unwrap());
+        }
+        lines
+    }
+
+    #[test]
+    fn counter_sends_windowed_delta_not_cumulative_value() {
+        let metrics = Metrics::new().unwrap();
+        let (exporter, receiver) = exporter_and_receiver();
+
+        metrics.total_builds.inc();
+        exporter.flush(&metrics).unwrap();
+        assert!(recv_lines(&receiver).contains(&"docsrs_total_builds:1|c".to_owned()));
+
+        metrics.total_builds.inc();
+        metrics.total_builds.inc();
+        exporter.flush(&metrics).unwrap();
+        assert!(recv_lines(&receiver).contains(&"docsrs_total_builds:2|c".to_owned()));
+    }
+
+    #[test]
+    fn histogram_sends_windowed_mean_and_skips_untouched_window() {
+        let metrics = Metrics::new().unwrap();
+        let (exporter, receiver) = exporter_and_receiver();
+
+        metrics.queue_wait_seconds.observe(1.0);
+        metrics.queue_wait_seconds.observe(3.0);
+        exporter.flush(&metrics).unwrap();
+        assert!(recv_lines(&receiver).contains(&"docsrs_queue_wait_seconds:2|h".to_owned()));
+
+        // Nothing observed since the last flush, so the stale mean shouldn't
+        // be resent.
+        exporter.flush(&metrics).unwrap();
+        assert!(recv_lines(&receiver)
+            .iter()
+            .all(|line| !line.starts_with("docsrs_queue_wait_seconds:")));
+    }
+
+    #[test]
+    fn dogstatsd_tags_formats_labels_and_handles_none() {
+        assert_eq!(dogstatsd_tags(&[]), "");
+
+        let mut route = prometheus::proto::LabelPair::new();
+        route.set_name("route".to_owned());
+        route.set_value("home".to_owned());
+
+        assert_eq!(dogstatsd_tags(&[route]), "|#route:home");
+    }
+}
@@ -0,0 +1,42 @@
+//! Web server startup.
+
+use crate::metrics::otlp::OtlpExporter;
+use crate::metrics::Metrics;
+use crate::Config;
+use failure::Error;
+use std::sync::Arc;
+
+/// Keeps the metrics exporters selected by [`start_metrics_exporters`]
+/// running for as long as this is alive; the caller must hold onto this
+/// for the life of the process. [`OtlpExporter`] stops its own background
+/// work on drop; the StatsD flush loop is a plain [`tokio::task::JoinHandle`],
+/// which only detaches (rather than stops) on drop, so `Drop` aborts it
+/// explicitly instead.
+pub(crate) struct MetricsExporterGuards {
+    _otlp: Option<OtlpExporter>,
+    statsd: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl Drop for MetricsExporterGuards {
+    fn drop(&mut self) {
+        if let Some(statsd) = &self.statsd {
+            statsd.abort();
+        }
+    }
+}
+
+/// Starts the metrics exporters selected by `config`, in addition to the
+/// Prometheus `/metrics` endpoint the web server always serves by scraping
+/// [`Metrics::gather`] directly.
+///
+/// An operator can enable any combination of OTLP and StatsD (or neither,
+/// relying purely on the Prometheus scrape) without recompiling.
+pub(crate) fn start_metrics_exporters(
+    metrics: &Arc<Metrics>,
+    config: &Config,
+) -> Result<MetricsExporterGuards, Error> {
+    Ok(MetricsExporterGuards {
+        _otlp: metrics.spawn_otlp_exporter(config.otlp.clone())?,
+        statsd: metrics.spawn_statsd_exporter(config.statsd.clone())?,
+    })
+}